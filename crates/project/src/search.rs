@@ -1,34 +1,167 @@
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
 use anyhow::{Context, Result};
 use client::proto;
-use globset::{Glob, GlobMatcher};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
 use itertools::Itertools;
 use language::{char_kind, Rope};
 use regex::{Regex, RegexBuilder};
 use smol::future::yield_now;
 use std::{
+    collections::HashMap,
     io::{BufRead, BufReader, Read},
     ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+/// Which regex engine compiled a [`SearchQuery::Regex`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegexEngine {
+    Default,
+    Pcre2,
+}
+
+#[derive(Clone)]
+enum CompiledRegex {
+    Default(Regex),
+    Pcre2(pcre2::bytes::Regex),
+}
+
+impl std::fmt::Debug for CompiledRegex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default(regex) => f.debug_tuple("Default").field(&regex.as_str()).finish(),
+            Self::Pcre2(regex) => f.debug_tuple("Pcre2").field(&regex.as_str()).finish(),
+        }
+    }
+}
+
+fn build_pcre2_regex(
+    query: &str,
+    case_sensitive: bool,
+    multiline: bool,
+) -> Result<pcre2::bytes::Regex> {
+    Ok(pcre2::bytes::RegexBuilder::new()
+        .caseless(!case_sensitive)
+        .multi_line(multiline)
+        .utf(true)
+        .ucp(true)
+        .build(query)?)
+}
+
+/// What a [`SearchQuery`] matches against: contents, path, or both.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchTarget {
+    Contents,
+    Path,
+    Both,
+}
+
+impl SearchTarget {
+    pub fn matches_contents(&self) -> bool {
+        matches!(self, Self::Contents | Self::Both)
+    }
+
+    pub fn matches_path(&self) -> bool {
+        matches!(self, Self::Path | Self::Both)
+    }
+
+    fn from_proto(match_contents: bool, match_path: bool) -> Self {
+        match (match_contents, match_path) {
+            (false, true) => Self::Path,
+            (_, true) => Self::Both,
+            _ => Self::Contents,
+        }
+    }
+}
+
+impl Default for SearchTarget {
+    fn default() -> Self {
+        Self::Contents
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SearchInputs {
     query: Arc<str>,
-    files_to_include: Vec<PathMatcher>,
-    files_to_exclude: Vec<PathMatcher>,
+    target: SearchTarget,
+    files_to_include: PathMatcherSet,
+    files_to_exclude: PathMatcherSet,
+    max_file_size: Option<u64>,
+    include_binary_files: bool,
+    min_depth: Option<usize>,
+    max_depth: Option<usize>,
 }
 
 impl SearchInputs {
     pub fn as_str(&self) -> &str {
         self.query.as_ref()
     }
+    pub fn target(&self) -> SearchTarget {
+        self.target
+    }
     pub fn files_to_include(&self) -> &[PathMatcher] {
-        &self.files_to_include
+        self.files_to_include.matchers()
     }
     pub fn files_to_exclude(&self) -> &[PathMatcher] {
-        &self.files_to_exclude
+        self.files_to_exclude.matchers()
+    }
+}
+
+/// A compiled include/exclude filter list, matched via one `GlobSet` pass
+/// plus a fallback `starts_with` check for plain, non-glob tokens.
+#[derive(Clone, Debug)]
+struct PathMatcherSet {
+    matchers: Vec<PathMatcher>,
+    glob_set: GlobSet,
+    // `glob_set`'s pattern at position `i` was built from `matchers[glob_set_indices[i]]`.
+    glob_set_indices: Vec<usize>,
+}
+
+impl PathMatcherSet {
+    fn new(matchers: Vec<PathMatcher>) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut glob_set_indices = Vec::new();
+        for (ix, matcher) in matchers.iter().enumerate() {
+            if matcher.is_glob() {
+                builder.add(matcher.as_glob());
+                glob_set_indices.push(ix);
+            }
+        }
+        let glob_set = builder
+            .build()
+            .expect("matchers were already validated by PathMatcher::new");
+        Self {
+            matchers,
+            glob_set,
+            glob_set_indices,
+        }
+    }
+
+    fn matchers(&self) -> &[PathMatcher] {
+        &self.matchers
+    }
+
+    fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    /// The verdict of the last matcher that matched `path` (gitignore-style).
+    fn last_match_verdict(&self, path: &Path) -> Option<bool> {
+        let mut last_match_ix = self
+            .glob_set
+            .matches(path)
+            .into_iter()
+            .map(|set_ix| self.glob_set_indices[set_ix])
+            .max();
+
+        for (ix, matcher) in self.matchers.iter().enumerate() {
+            if !matcher.is_glob() && matcher.is_match(path) {
+                last_match_ix = last_match_ix.max(Some(ix));
+            }
+        }
+
+        last_match_ix.map(|ix| !self.matchers[ix].is_negated())
     }
 }
 #[derive(Clone, Debug)]
@@ -40,7 +173,8 @@ pub enum SearchQuery {
         inner: SearchInputs,
     },
     Regex {
-        regex: Regex,
+        regex: CompiledRegex,
+        engine: RegexEngine,
 
         multiline: bool,
         whole_word: bool,
@@ -53,10 +187,14 @@ pub enum SearchQuery {
 pub struct PathMatcher {
     maybe_path: PathBuf,
     glob: GlobMatcher,
+    negated: bool,
 }
 
 impl std::fmt::Display for PathMatcher {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negated {
+            write!(f, "!")?;
+        }
         self.maybe_path.to_string_lossy().fmt(f)
     }
 }
@@ -64,14 +202,38 @@ impl std::fmt::Display for PathMatcher {
 impl PathMatcher {
     pub fn new(maybe_glob: &str) -> Result<Self, globset::Error> {
         Ok(PathMatcher {
-            glob: Glob::new(&maybe_glob)?.compile_matcher(),
+            glob: Glob::new(maybe_glob)?.compile_matcher(),
             maybe_path: PathBuf::from(maybe_glob),
+            negated: false,
         })
     }
 
+    /// Marks this matcher as excluding rather than including a matched path.
+    pub fn negated(mut self) -> Self {
+        self.negated = true;
+        self
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
     pub fn is_match<P: AsRef<Path>>(&self, other: P) -> bool {
         other.as_ref().starts_with(&self.maybe_path) || self.glob.is_match(other)
     }
+
+    /// Whether this matcher's source text actually contains glob metacharacters.
+    fn is_glob(&self) -> bool {
+        self.maybe_path
+            .to_string_lossy()
+            .chars()
+            .any(|c| matches!(c, '*' | '?' | '[' | ']' | '{' | '}'))
+    }
+
+    fn as_glob(&self) -> Glob {
+        Glob::new(&self.maybe_path.to_string_lossy())
+            .expect("source text was already validated in PathMatcher::new")
+    }
 }
 
 impl SearchQuery {
@@ -79,6 +241,7 @@ impl SearchQuery {
         query: impl ToString,
         whole_word: bool,
         case_sensitive: bool,
+        target: SearchTarget,
         files_to_include: Vec<PathMatcher>,
         files_to_exclude: Vec<PathMatcher>,
     ) -> Self {
@@ -89,8 +252,13 @@ impl SearchQuery {
             .build(&[&query]);
         let inner = SearchInputs {
             query: query.into(),
-            files_to_exclude,
-            files_to_include,
+            target,
+            files_to_exclude: PathMatcherSet::new(files_to_exclude),
+            files_to_include: PathMatcherSet::new(files_to_include),
+            max_file_size: None,
+            include_binary_files: false,
+            min_depth: None,
+            max_depth: None,
         };
         Self::Text {
             search: Arc::new(search),
@@ -104,8 +272,31 @@ impl SearchQuery {
         query: impl ToString,
         whole_word: bool,
         case_sensitive: bool,
+        target: SearchTarget,
+        files_to_include: Vec<PathMatcher>,
+        files_to_exclude: Vec<PathMatcher>,
+    ) -> Result<Self> {
+        Self::regex_with_engine(
+            query,
+            whole_word,
+            case_sensitive,
+            target,
+            files_to_include,
+            files_to_exclude,
+            RegexEngine::Default,
+        )
+    }
+
+    /// Falls back to PCRE2 if `engine` is `Default` and the pattern uses a
+    /// construct the `regex` crate rejects.
+    pub fn regex_with_engine(
+        query: impl ToString,
+        whole_word: bool,
+        case_sensitive: bool,
+        target: SearchTarget,
         files_to_include: Vec<PathMatcher>,
         files_to_exclude: Vec<PathMatcher>,
+        engine: RegexEngine,
     ) -> Result<Self> {
         let mut query = query.to_string();
         let initial_query = Arc::from(query.as_str());
@@ -118,17 +309,38 @@ impl SearchQuery {
         }
 
         let multiline = query.contains('\n') || query.contains("\\n");
-        let regex = RegexBuilder::new(&query)
-            .case_insensitive(!case_sensitive)
-            .multi_line(multiline)
-            .build()?;
+
+        let (regex, engine) = match engine {
+            RegexEngine::Pcre2 => (
+                CompiledRegex::Pcre2(build_pcre2_regex(&query, case_sensitive, multiline)?),
+                RegexEngine::Pcre2,
+            ),
+            RegexEngine::Default => match RegexBuilder::new(&query)
+                .case_insensitive(!case_sensitive)
+                .multi_line(multiline)
+                .build()
+            {
+                Ok(regex) => (CompiledRegex::Default(regex), RegexEngine::Default),
+                Err(_) => (
+                    CompiledRegex::Pcre2(build_pcre2_regex(&query, case_sensitive, multiline)?),
+                    RegexEngine::Pcre2,
+                ),
+            },
+        };
+
         let inner = SearchInputs {
             query: initial_query,
-            files_to_exclude,
-            files_to_include,
+            target,
+            files_to_exclude: PathMatcherSet::new(files_to_exclude),
+            files_to_include: PathMatcherSet::new(files_to_include),
+            max_file_size: None,
+            include_binary_files: false,
+            min_depth: None,
+            max_depth: None,
         };
         Ok(Self::Regex {
             regex,
+            engine,
             multiline,
             whole_word,
             case_sensitive,
@@ -137,23 +349,38 @@ impl SearchQuery {
     }
 
     pub fn from_proto(message: proto::SearchProject) -> Result<Self> {
-        if message.regex {
-            Self::regex(
+        let target = SearchTarget::from_proto(message.match_contents, message.match_path);
+        let mut query = if message.regex {
+            Self::regex_with_engine(
                 message.query,
                 message.whole_word,
                 message.case_sensitive,
+                target,
                 deserialize_path_matches(&message.files_to_include)?,
                 deserialize_path_matches(&message.files_to_exclude)?,
-            )
+                if message.regex_engine_pcre2 {
+                    RegexEngine::Pcre2
+                } else {
+                    RegexEngine::Default
+                },
+            )?
         } else {
-            Ok(Self::text(
+            Self::text(
                 message.query,
                 message.whole_word,
                 message.case_sensitive,
+                target,
                 deserialize_path_matches(&message.files_to_include)?,
                 deserialize_path_matches(&message.files_to_exclude)?,
-            ))
+            )
+        };
+        if let Some(min_depth) = message.min_depth {
+            query = query.with_min_depth(min_depth as usize);
+        }
+        if let Some(max_depth) = message.max_depth {
+            query = query.with_max_depth(max_depth as usize);
         }
+        Ok(query)
     }
 
     pub fn to_proto(&self, project_id: u64) -> proto::SearchProject {
@@ -161,8 +388,17 @@ impl SearchQuery {
             project_id,
             query: self.as_str().to_string(),
             regex: self.is_regex(),
+            regex_engine_pcre2: matches!(
+                self,
+                Self::Regex {
+                    engine: RegexEngine::Pcre2,
+                    ..
+                }
+            ),
             whole_word: self.whole_word(),
             case_sensitive: self.case_sensitive(),
+            match_contents: self.search_target().matches_contents(),
+            match_path: self.search_target().matches_path(),
             files_to_include: self
                 .files_to_include()
                 .iter()
@@ -173,17 +409,35 @@ impl SearchQuery {
                 .iter()
                 .map(|matcher| matcher.to_string())
                 .join(","),
+            min_depth: self.min_depth().map(|depth| depth as u32),
+            max_depth: self.max_depth().map(|depth| depth as u32),
         }
     }
 
-    pub fn detect<T: Read>(&self, stream: T) -> Result<bool> {
+    /// Skips files over `max_file_size` or (unless opted into) binary content.
+    pub fn detect<T: Read>(&self, stream: T, file_size: Option<u64>) -> Result<bool> {
         if self.as_str().is_empty() {
             return Ok(false);
         }
 
+        if !self.search_target().matches_contents() {
+            return Ok(true);
+        }
+
+        if let Some(max_file_size) = self.max_file_size() {
+            if file_size.is_some_and(|file_size| file_size > max_file_size) {
+                return Ok(false);
+            }
+        }
+
+        let mut reader = BufReader::new(stream);
+        if !self.include_binary_files() && contains_binary_content(&mut reader)? {
+            return Ok(false);
+        }
+
         match self {
             Self::Text { search, .. } => {
-                let mat = search.stream_find_iter(stream).next();
+                let mat = search.stream_find_iter(reader).next();
                 match mat {
                     Some(Ok(_)) => Ok(true),
                     Some(Err(err)) => Err(err.into()),
@@ -192,33 +446,54 @@ impl SearchQuery {
             }
             Self::Regex {
                 regex, multiline, ..
-            } => {
-                let mut reader = BufReader::new(stream);
-                if *multiline {
-                    let mut text = String::new();
-                    if let Err(err) = reader.read_to_string(&mut text) {
-                        Err(err.into())
+            } => match regex {
+                CompiledRegex::Default(regex) => {
+                    if *multiline {
+                        let mut text = String::new();
+                        if let Err(err) = reader.read_to_string(&mut text) {
+                            Err(err.into())
+                        } else {
+                            Ok(regex.find(&text).is_some())
+                        }
                     } else {
-                        Ok(regex.find(&text).is_some())
+                        for line in reader.lines() {
+                            let line = line?;
+                            if regex.find(&line).is_some() {
+                                return Ok(true);
+                            }
+                        }
+                        Ok(false)
                     }
-                } else {
-                    for line in reader.lines() {
-                        let line = line?;
-                        if regex.find(&line).is_some() {
-                            return Ok(true);
+                }
+                CompiledRegex::Pcre2(regex) => {
+                    if *multiline {
+                        let mut bytes = Vec::new();
+                        reader.read_to_end(&mut bytes)?;
+                        Ok(regex.find(&bytes)?.is_some())
+                    } else {
+                        let mut line = String::new();
+                        loop {
+                            line.clear();
+                            if reader.read_line(&mut line)? == 0 {
+                                break;
+                            }
+                            let line = line.trim_end_matches(['\n', '\r']);
+                            if regex.find(line.as_bytes())?.is_some() {
+                                return Ok(true);
+                            }
                         }
+                        Ok(false)
                     }
-                    Ok(false)
                 }
-            }
+            },
         }
     }
 
-    pub async fn search(&self, rope: &Rope) -> Vec<Range<usize>> {
+    pub async fn search(&self, rope: &Rope) -> Result<Vec<Range<usize>>> {
         const YIELD_INTERVAL: usize = 20000;
 
         if self.as_str().is_empty() {
-            return Default::default();
+            return Ok(Default::default());
         }
 
         let mut matches = Vec::new();
@@ -249,42 +524,112 @@ impl SearchQuery {
             }
             Self::Regex {
                 regex, multiline, ..
-            } => {
-                if *multiline {
-                    let text = rope.to_string();
-                    for (ix, mat) in regex.find_iter(&text).enumerate() {
-                        if (ix + 1) % YIELD_INTERVAL == 0 {
-                            yield_now().await;
+            } => match regex {
+                CompiledRegex::Default(regex) => {
+                    if *multiline {
+                        let text = rope.to_string();
+                        for (ix, mat) in regex.find_iter(&text).enumerate() {
+                            if (ix + 1) % YIELD_INTERVAL == 0 {
+                                yield_now().await;
+                            }
+
+                            matches.push(mat.start()..mat.end());
                         }
+                    } else {
+                        let mut line = String::new();
+                        let mut line_offset = 0;
+                        for (chunk_ix, chunk) in rope.chunks().chain(["\n"]).enumerate() {
+                            if (chunk_ix + 1) % YIELD_INTERVAL == 0 {
+                                yield_now().await;
+                            }
+
+                            for (newline_ix, text) in chunk.split('\n').enumerate() {
+                                if newline_ix > 0 {
+                                    for mat in regex.find_iter(&line) {
+                                        let start = line_offset + mat.start();
+                                        let end = line_offset + mat.end();
+                                        matches.push(start..end);
+                                    }
 
-                        matches.push(mat.start()..mat.end());
+                                    line_offset += line.len() + 1;
+                                    line.clear();
+                                }
+                                line.push_str(text);
+                            }
+                        }
                     }
-                } else {
-                    let mut line = String::new();
-                    let mut line_offset = 0;
-                    for (chunk_ix, chunk) in rope.chunks().chain(["\n"]).enumerate() {
-                        if (chunk_ix + 1) % YIELD_INTERVAL == 0 {
-                            yield_now().await;
+                }
+                CompiledRegex::Pcre2(regex) => {
+                    if *multiline {
+                        let text = rope.to_string();
+                        for (ix, mat) in regex.find_iter(text.as_bytes()).enumerate() {
+                            if (ix + 1) % YIELD_INTERVAL == 0 {
+                                yield_now().await;
+                            }
+
+                            let mat = mat.context("pcre2 match failed")?;
+                            matches.push(mat.start()..mat.end());
                         }
+                    } else {
+                        let mut line = String::new();
+                        let mut line_offset = 0;
+                        for (chunk_ix, chunk) in rope.chunks().chain(["\n"]).enumerate() {
+                            if (chunk_ix + 1) % YIELD_INTERVAL == 0 {
+                                yield_now().await;
+                            }
 
-                        for (newline_ix, text) in chunk.split('\n').enumerate() {
-                            if newline_ix > 0 {
-                                for mat in regex.find_iter(&line) {
-                                    let start = line_offset + mat.start();
-                                    let end = line_offset + mat.end();
-                                    matches.push(start..end);
-                                }
+                            for (newline_ix, text) in chunk.split('\n').enumerate() {
+                                if newline_ix > 0 {
+                                    for mat in regex.find_iter(line.as_bytes()) {
+                                        let mat = mat.context("pcre2 match failed")?;
+                                        let start = line_offset + mat.start();
+                                        let end = line_offset + mat.end();
+                                        matches.push(start..end);
+                                    }
 
-                                line_offset += line.len() + 1;
-                                line.clear();
+                                    line_offset += line.len() + 1;
+                                    line.clear();
+                                }
+                                line.push_str(text);
                             }
-                            line.push_str(text);
                         }
                     }
                 }
-            }
+            },
         }
-        matches
+        Ok(matches)
+    }
+
+    /// Mirrors `search`, but matches against a path string instead.
+    pub fn search_path(&self, path: &Path) -> Result<Vec<Range<usize>>> {
+        if self.as_str().is_empty() {
+            return Ok(Default::default());
+        }
+
+        let path = path.to_string_lossy();
+        let ranges = match self {
+            Self::Text {
+                search, whole_word, ..
+            } => search
+                .find_iter(path.as_bytes())
+                .filter(|mat| !*whole_word || word_bounded(&path, mat.start(), mat.end()))
+                .map(|mat| mat.start()..mat.end())
+                .collect(),
+            Self::Regex { regex, .. } => match regex {
+                CompiledRegex::Default(regex) => regex
+                    .find_iter(&path)
+                    .map(|mat| mat.start()..mat.end())
+                    .collect(),
+                CompiledRegex::Pcre2(regex) => regex
+                    .find_iter(path.as_bytes())
+                    .map(|mat| {
+                        mat.context("pcre2 match failed")
+                            .map(|mat| mat.start()..mat.end())
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            },
+        };
+        Ok(ranges)
     }
 
     pub fn as_str(&self) -> &str {
@@ -309,6 +654,10 @@ impl SearchQuery {
         matches!(self, Self::Regex { .. })
     }
 
+    pub fn search_target(&self) -> SearchTarget {
+        self.as_inner().target()
+    }
+
     pub fn files_to_include(&self) -> &[PathMatcher] {
         self.as_inner().files_to_include()
     }
@@ -318,19 +667,24 @@ impl SearchQuery {
     }
 
     pub fn file_matches(&self, file_path: Option<&Path>) -> bool {
+        let inner = self.as_inner();
         match file_path {
             Some(file_path) => {
-                !self
-                    .files_to_exclude()
-                    .iter()
-                    .any(|exclude_glob| exclude_glob.is_match(file_path))
-                    && (self.files_to_include().is_empty()
-                        || self
-                            .files_to_include()
-                            .iter()
-                            .any(|include_glob| include_glob.is_match(file_path)))
+                if !depth_in_range(file_path, inner.min_depth, inner.max_depth) {
+                    return false;
+                }
+                let included = inner.files_to_include.is_empty()
+                    || inner
+                        .files_to_include
+                        .last_match_verdict(file_path)
+                        .unwrap_or(false);
+                let excluded = inner
+                    .files_to_exclude
+                    .last_match_verdict(file_path)
+                    .unwrap_or(false);
+                included && !excluded
             }
-            None => self.files_to_include().is_empty(),
+            None => inner.files_to_include.is_empty(),
         }
     }
     pub fn as_inner(&self) -> &SearchInputs {
@@ -338,18 +692,181 @@ impl SearchQuery {
             Self::Regex { inner, .. } | Self::Text { inner, .. } => inner,
         }
     }
+
+    fn as_inner_mut(&mut self) -> &mut SearchInputs {
+        match self {
+            Self::Regex { inner, .. } | Self::Text { inner, .. } => inner,
+        }
+    }
+
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.as_inner().max_file_size
+    }
+
+    pub fn include_binary_files(&self) -> bool {
+        self.as_inner().include_binary_files
+    }
+
+    /// Skip files larger than `max_file_size` bytes in `detect`.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.as_inner_mut().max_file_size = Some(max_file_size);
+        self
+    }
+
+    /// Opt into scanning files that `detect` would otherwise treat as binary.
+    pub fn with_binary_search(mut self, include_binary_files: bool) -> Self {
+        self.as_inner_mut().include_binary_files = include_binary_files;
+        self
+    }
+
+    pub fn min_depth(&self) -> Option<usize> {
+        self.as_inner().min_depth
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.as_inner().max_depth
+    }
+
+    /// Only match candidates at least `min_depth` directories deep (the file
+    /// name itself is not counted).
+    pub fn with_min_depth(mut self, min_depth: usize) -> Self {
+        self.as_inner_mut().min_depth = Some(min_depth);
+        self
+    }
+
+    /// Only match candidates at most `max_depth` directories deep (the file
+    /// name itself is not counted).
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.as_inner_mut().max_depth = Some(max_depth);
+        self
+    }
+}
+
+/// Whether `path`'s directory depth (its component count, excluding the
+/// file name itself) falls within `[min_depth, max_depth]`.
+fn depth_in_range(path: &Path, min_depth: Option<usize>, max_depth: Option<usize>) -> bool {
+    if min_depth.is_none() && max_depth.is_none() {
+        return true;
+    }
+    let depth = path.components().count().saturating_sub(1);
+    depth >= min_depth.unwrap_or(0) && depth <= max_depth.unwrap_or(usize::MAX)
+}
+
+/// Built-in `type:NAME` -> glob table, modeled on ripgrep's file types.
+const BUILTIN_FILE_TYPES: &[(&str, &[&str])] = &[
+    ("rust", &["*.rs"]),
+    ("js", &["*.js", "*.jsx", "*.mjs", "*.cjs"]),
+    ("ts", &["*.ts", "*.tsx"]),
+    ("py", &["*.py", "*.pyi"]),
+    ("go", &["*.go"]),
+    ("c", &["*.c", "*.h"]),
+    ("cpp", &["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh"]),
+    ("md", &["*.md", "*.markdown"]),
+    ("json", &["*.json"]),
+    ("toml", &["*.toml"]),
+    ("yaml", &["*.yml", "*.yaml"]),
+];
+
+/// Resolves `type:NAME` tokens to globs, falling back to [`BUILTIN_FILE_TYPES`].
+#[derive(Clone, Debug, Default)]
+pub struct FileTypeRegistry {
+    custom_types: HashMap<String, Vec<String>>,
+}
+
+impl FileTypeRegistry {
+    pub fn new(custom_types: HashMap<String, Vec<String>>) -> Self {
+        Self { custom_types }
+    }
+
+    fn globs_for(&self, name: &str) -> Option<Vec<String>> {
+        if let Some(globs) = self.custom_types.get(name) {
+            return Some(globs.clone());
+        }
+        BUILTIN_FILE_TYPES
+            .iter()
+            .find(|(type_name, _)| *type_name == name)
+            .map(|(_, globs)| globs.iter().map(|glob| glob.to_string()).collect())
+    }
+}
+
+/// Whether the stream's first buffered chunk contains a NUL byte.
+fn contains_binary_content<T: BufRead>(reader: &mut T) -> Result<bool> {
+    Ok(reader.fill_buf()?.contains(&0))
+}
+
+/// Parses a human-friendly size like `"512k"`, `"2M"`, or `"1G"` into bytes.
+pub fn parse_file_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let (digits, multiplier) = match input.chars().last() {
+        Some('k' | 'K') => (&input[..input.len() - 1], 1024),
+        Some('m' | 'M') => (&input[..input.len() - 1], 1024 * 1024),
+        Some('g' | 'G') => (&input[..input.len() - 1], 1024 * 1024 * 1024),
+        _ => (input, 1),
+    };
+    let value: u64 = digits
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid file size `{input}`"))?;
+    Ok(value * multiplier)
+}
+
+/// Whether `text[start..end]` sits on word boundaries.
+fn word_bounded(text: &str, start: usize, end: usize) -> bool {
+    let prev_kind = text[..start].chars().next_back().map(char_kind);
+    let start_kind = char_kind(text[start..].chars().next().unwrap());
+    let end_kind = char_kind(text[..end].chars().next_back().unwrap());
+    let next_kind = text[end..].chars().next().map(char_kind);
+    Some(start_kind) != prev_kind && Some(end_kind) != next_kind
 }
 
 fn deserialize_path_matches(glob_set: &str) -> anyhow::Result<Vec<PathMatcher>> {
+    parse_path_matches(glob_set, &FileTypeRegistry::default())
+}
+
+/// Like `deserialize_path_matches`, but against a caller-supplied registry
+/// (e.g. one with user-defined `type:NAME` entries from settings).
+pub fn parse_path_matches(
+    glob_set: &str,
+    file_types: &FileTypeRegistry,
+) -> anyhow::Result<Vec<PathMatcher>> {
     glob_set
         .split(',')
         .map(str::trim)
         .filter(|glob_str| !glob_str.is_empty())
-        .map(|glob_str| {
-            PathMatcher::new(glob_str)
-                .with_context(|| format!("deserializing path match glob {glob_str}"))
-        })
-        .collect()
+        .map(|token| expand_file_type_token(token, file_types))
+        .collect::<Result<Vec<_>>>()
+        .map(|matchers| matchers.into_iter().flatten().collect())
+}
+
+fn expand_file_type_token(
+    token: &str,
+    file_types: &FileTypeRegistry,
+) -> anyhow::Result<Vec<PathMatcher>> {
+    let (negated, token) = match token.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+
+    let matchers = if let Some(type_name) = token.strip_prefix("type:") {
+        file_types
+            .globs_for(type_name)
+            .with_context(|| format!("unknown file type `{type_name}`"))?
+            .iter()
+            .map(|glob_str| {
+                PathMatcher::new(glob_str)
+                    .with_context(|| format!("deserializing path match glob {glob_str}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        vec![PathMatcher::new(token)
+            .with_context(|| format!("deserializing path match glob {token}"))?]
+    };
+
+    Ok(if negated {
+        matchers.into_iter().map(PathMatcher::negated).collect()
+    } else {
+        matchers
+    })
 }
 
 #[cfg(test)]
@@ -399,4 +916,144 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn type_token_expands_to_builtin_globs() {
+        let matchers = parse_path_matches("type:rust", &FileTypeRegistry::default()).unwrap();
+        assert!(matchers.iter().any(|matcher| matcher.is_match("foo.rs")));
+        assert!(!matchers.iter().any(|matcher| matcher.is_match("foo.js")));
+    }
+
+    #[test]
+    fn type_token_prefers_custom_type_over_builtin() {
+        let file_types = FileTypeRegistry::new(HashMap::from([(
+            "rust".to_string(),
+            vec!["*.rs".to_string(), "*.rs.in".to_string()],
+        )]));
+        let matchers = parse_path_matches("type:rust", &file_types).unwrap();
+        assert!(matchers.iter().any(|matcher| matcher.is_match("foo.rs.in")));
+    }
+
+    #[test]
+    fn unknown_type_token_is_an_error() {
+        assert!(parse_path_matches("type:cobol", &FileTypeRegistry::default()).is_err());
+    }
+
+    #[test]
+    fn negated_pattern_carves_out_an_exception() {
+        let matchers = PathMatcherSet::new(
+            parse_path_matches(
+                "src/**,!src/**/*.generated.rs",
+                &FileTypeRegistry::default(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            matchers.last_match_verdict(Path::new("src/foo.rs")),
+            Some(true)
+        );
+        assert_eq!(
+            matchers.last_match_verdict(Path::new("src/foo.generated.rs")),
+            Some(false)
+        );
+        assert_eq!(matchers.last_match_verdict(Path::new("other.rs")), None);
+    }
+
+    #[test]
+    fn later_pattern_overrides_earlier_one() {
+        let matchers = PathMatcherSet::new(
+            parse_path_matches("!src/**/*.rs,src/**/*.rs", &FileTypeRegistry::default()).unwrap(),
+        );
+        assert_eq!(
+            matchers.last_match_verdict(Path::new("src/foo.rs")),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn plain_path_tokens_still_match_by_prefix_alongside_globs() {
+        let matchers = PathMatcherSet::new(
+            parse_path_matches("vendor,*.generated.rs", &FileTypeRegistry::default()).unwrap(),
+        );
+        assert_eq!(
+            matchers.last_match_verdict(Path::new("vendor/lib.rs")),
+            Some(true)
+        );
+        assert_eq!(
+            matchers.last_match_verdict(Path::new("foo.generated.rs")),
+            Some(true)
+        );
+        assert_eq!(matchers.last_match_verdict(Path::new("src/lib.rs")), None);
+    }
+
+    #[test]
+    fn search_path_matches_the_path_string() {
+        let query = SearchQuery::text("lib", false, false, SearchTarget::Path, vec![], vec![]);
+        let matches = query.search_path(Path::new("src/lib.rs")).unwrap();
+        assert_eq!(matches, vec![4..7]);
+        assert!(query
+            .search_path(Path::new("src/main.rs"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn search_target_decodes_from_proto_flags() {
+        assert_eq!(
+            SearchTarget::from_proto(true, false),
+            SearchTarget::Contents
+        );
+        assert_eq!(SearchTarget::from_proto(false, true), SearchTarget::Path);
+        assert_eq!(SearchTarget::from_proto(true, true), SearchTarget::Both);
+    }
+
+    #[test]
+    fn parses_human_friendly_file_sizes() {
+        assert_eq!(parse_file_size("512").unwrap(), 512);
+        assert_eq!(parse_file_size("512k").unwrap(), 512 * 1024);
+        assert_eq!(parse_file_size("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_file_size("1G").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_file_size("two gigs").is_err());
+    }
+
+    #[test]
+    fn detect_skips_files_over_the_size_limit() {
+        let query = SearchQuery::text("foo", false, false, SearchTarget::Contents, vec![], vec![])
+            .with_max_file_size(10);
+        assert!(!query.detect(b"foo".as_slice(), Some(11)).unwrap());
+        assert!(query.detect(b"foo".as_slice(), Some(3)).unwrap());
+        assert!(query.detect(b"foo".as_slice(), None).unwrap());
+    }
+
+    #[test]
+    fn detect_skips_binary_content_unless_opted_in() {
+        let binary = b"\0foo".to_vec();
+        let query = SearchQuery::text("foo", false, false, SearchTarget::Contents, vec![], vec![]);
+        assert!(!query.detect(binary.as_slice(), None).unwrap());
+
+        let query = query.with_binary_search(true);
+        assert!(query.detect(binary.as_slice(), None).unwrap());
+    }
+
+    #[test]
+    fn detect_ignores_content_for_path_only_targets() {
+        let query = SearchQuery::text("foo", false, false, SearchTarget::Path, vec![], vec![])
+            .with_max_file_size(1);
+        assert!(query.detect(b"\0bar".as_slice(), Some(100)).unwrap());
+    }
+
+    #[test]
+    fn depth_bounds_restrict_which_paths_match() {
+        let query = SearchQuery::text("foo", false, false, SearchTarget::Contents, vec![], vec![])
+            .with_max_depth(2);
+        assert!(query.file_matches(Some(Path::new("Cargo.toml"))));
+        assert!(query.file_matches(Some(Path::new("crates/project/Cargo.toml"))));
+        assert!(!query.file_matches(Some(Path::new("crates/project/foo/Cargo.toml"))));
+
+        let query = SearchQuery::text("foo", false, false, SearchTarget::Contents, vec![], vec![])
+            .with_min_depth(2);
+        assert!(!query.file_matches(Some(Path::new("Cargo.toml"))));
+        assert!(!query.file_matches(Some(Path::new("crates/Cargo.toml"))));
+        assert!(query.file_matches(Some(Path::new("crates/project/Cargo.toml"))));
+    }
 }